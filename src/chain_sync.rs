@@ -0,0 +1,92 @@
+// Chain requests used to go out as a broadcast over a `chains` gossip topic,
+// so every peer got spammed with a chain nobody but the joining node asked
+// for. This is a plain request/response protocol instead - sync from one peer.
+
+use async_trait::async_trait;
+use futures::io::{AsyncRead, AsyncWrite};
+use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed};
+use libp2p::core::ProtocolName;
+use libp2p::request_response::RequestResponseCodec;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use super::Block;
+
+// a chain is small enough in this demo that 16MiB is a generous upper bound,
+// and keeps a malicious peer from asking us to allocate an unbounded buffer.
+const MAX_SYNC_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChainRequest {
+    pub from: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChainResponse {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainSyncProtocol;
+
+impl ProtocolName for ChainSyncProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        b"/chain-sync/1.0.0"
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ChainSyncCodec;
+
+#[async_trait]
+impl RequestResponseCodec for ChainSyncCodec {
+    type Protocol = ChainSyncProtocol;
+    type Request = ChainRequest;
+    type Response = ChainResponse;
+
+    async fn read_request<T>(&mut self, _: &ChainSyncProtocol, io: &mut T) -> io::Result<ChainRequest>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_length_prefixed(io, MAX_SYNC_MESSAGE_SIZE).await?;
+        serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &ChainSyncProtocol,
+        io: &mut T,
+    ) -> io::Result<ChainResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_length_prefixed(io, MAX_SYNC_MESSAGE_SIZE).await?;
+        serde_json::from_slice(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &ChainSyncProtocol,
+        io: &mut T,
+        request: ChainRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let bytes = serde_json::to_vec(&request).expect("can serialize chain request");
+        write_length_prefixed(io, bytes).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &ChainSyncProtocol,
+        io: &mut T,
+        response: ChainResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let bytes = serde_json::to_vec(&response).expect("can serialize chain response");
+        write_length_prefixed(io, bytes).await
+    }
+}
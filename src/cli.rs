@@ -0,0 +1,53 @@
+// CLI/env parsing that doesn't belong to any one behaviour.
+
+use libp2p::Multiaddr;
+use log::error;
+
+const RENDEZVOUS_FLAG: &str = "--rendezvous";
+const EXTERNAL_ADDRESS_FLAG: &str = "--external-address";
+
+// grabs every `--rendezvous <multiaddr>` flag on the command line - how nodes
+// outside the local network find peers at all.
+pub fn rendezvous_servers() -> Vec<Multiaddr> {
+    let mut servers = Vec::new();
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg != RENDEZVOUS_FLAG {
+            continue;
+        }
+        match args.next() {
+            Some(raw) => match raw.parse::<Multiaddr>() {
+                Ok(addr) => servers.push(addr),
+                Err(e) => error!("invalid --rendezvous address '{}': {}", raw, e),
+            },
+            None => error!("--rendezvous flag given with no address"),
+        }
+    }
+    servers
+}
+
+// the address other nodes should dial to reach us. nothing here does NAT
+// traversal or address discovery, so without this flag rendezvous
+// registration has no dialable address to publish.
+pub fn external_address() -> Option<Multiaddr> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg != EXTERNAL_ADDRESS_FLAG {
+            continue;
+        }
+        return match args.next() {
+            Some(raw) => match raw.parse::<Multiaddr>() {
+                Ok(addr) => Some(addr),
+                Err(e) => {
+                    error!("invalid --external-address '{}': {}", raw, e);
+                    None
+                }
+            },
+            None => {
+                error!("--external-address flag given with no address");
+                None
+            }
+        };
+    }
+    None
+}
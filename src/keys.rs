@@ -0,0 +1,72 @@
+// Persistent node identity.
+// Without this, a node's Keypair (and therefore its PeerId) is regenerated
+// on every startup, so peers can never recognize the same node twice.
+
+use libp2p::identity::{self, ed25519};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+pub const DEFAULT_KEY_PATH: &str = "./node_key";
+const KEY_PATH_FLAG: &str = "--key-file";
+const KEY_PATH_ENV: &str = "NODE_KEY_PATH";
+
+// where's the node key file: `--key-file <path>` CLI arg, then NODE_KEY_PATH, then the default.
+pub fn resolve_key_path() -> PathBuf {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == KEY_PATH_FLAG {
+            if let Some(path) = args.next() {
+                return PathBuf::from(path);
+            }
+        }
+    }
+    if let Ok(path) = std::env::var(KEY_PATH_ENV) {
+        return PathBuf::from(path);
+    }
+    PathBuf::from(DEFAULT_KEY_PATH)
+}
+
+// reads the node's ed25519 key from `path`, or generates a fresh one and
+// persists it (0600 perms) if the file doesn't exist yet.
+pub fn load_or_generate(path: &Path) -> identity::Keypair {
+    match fs::read(path) {
+        Ok(mut bytes) => {
+            let secret = ed25519::SecretKey::from_bytes(&mut bytes)
+                .expect("node key file holds a valid ed25519 secret key");
+            identity::Keypair::Ed25519(ed25519::Keypair::from(secret))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let keypair = identity::Keypair::generate_ed25519();
+            persist(path, &keypair);
+            keypair
+        }
+        // anything other than "doesn't exist yet" (permissions, EIO, ...) should
+        // not be treated as a reason to generate and overwrite a new identity.
+        Err(e) => panic!("can't read node key file {}: {}", path.display(), e),
+    }
+}
+
+fn persist(path: &Path, keypair: &identity::Keypair) {
+    let secret_bytes = match keypair {
+        identity::Keypair::Ed25519(kp) => kp.secret().as_ref().to_vec(),
+        _ => unreachable!("load_or_generate only ever generates ed25519 keys"),
+    };
+
+    let mut file = fs::File::create(path).expect("can create node key file");
+    file.write_all(&secret_bytes)
+        .expect("can write node key file");
+
+    #[cfg(unix)]
+    {
+        let mut perms = file
+            .metadata()
+            .expect("can read node key file metadata")
+            .permissions();
+        perms.set_mode(0o600);
+        fs::set_permissions(path, perms).expect("can set node key file permissions");
+    }
+}
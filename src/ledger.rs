@@ -0,0 +1,73 @@
+// Account balances, derived by replaying transactions rather than trusted
+// directly. `is_block_valid` checks a block's header (hash, difficulty,
+// id linkage) and its transactions' signatures; this is the other half -
+// whether the transfers it describes are even possible.
+
+use std::collections::HashMap;
+
+use crate::tx::Transaction;
+use super::Block;
+
+#[derive(Debug, Default, Clone)]
+pub struct Ledger {
+    balances: HashMap<String, u64>,
+    nonces: HashMap<String, u64>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // applies a block's transactions in order. false (ledger untouched) if any
+    // of them would take a balance negative or use a nonce out of sequence -
+    // a block goes in cleanly in full, or not at all.
+    pub fn enact(&mut self, block: &Block) -> bool {
+        let mut next = self.clone();
+        for transaction in &block.transactions {
+            if !next.apply(transaction) {
+                return false;
+            }
+        }
+        *self = next;
+        true
+    }
+
+    // replay a whole chain from genesis through a fresh ledger. only comes
+    // back Some if every block's state transition actually holds up.
+    pub fn enact_chain(chain: &[Block]) -> Option<Self> {
+        let mut ledger = Self::new();
+        for block in chain {
+            if !ledger.enact(block) {
+                return None;
+            }
+        }
+        Some(ledger)
+    }
+
+    fn apply(&mut self, transaction: &Transaction) -> bool {
+        let expected_nonce = *self.nonces.get(&transaction.from).unwrap_or(&0);
+        if transaction.nonce != expected_nonce {
+            return false;
+        }
+        let balance = *self.balances.get(&transaction.from).unwrap_or(&0);
+        if balance < transaction.amount {
+            return false;
+        }
+        self.balances
+            .insert(transaction.from.clone(), balance - transaction.amount);
+        *self.balances.entry(transaction.to.clone()).or_insert(0) += transaction.amount;
+        self.nonces
+            .insert(transaction.from.clone(), expected_nonce + 1);
+        true
+    }
+
+    pub fn balances(&self) -> &HashMap<String, u64> {
+        &self.balances
+    }
+
+    // the next nonce `account` should use - 0 if it's never sent anything.
+    pub fn next_nonce(&self, account: &str) -> u64 {
+        *self.nonces.get(account).unwrap_or(&0)
+    }
+}
@@ -1,15 +1,22 @@
 // This is a rewrite from https://blog.logrocket.com/how-to-build-a-blockchain-in-rust/
 // This is just for me to practice Rust as well as build a blockchain from scratch to really see what goes into one.
 
+pub mod chain_sync;
+pub mod cli;
+pub mod keys;
+pub mod ledger;
 pub mod p2p;
+pub mod tx;
 
 use chrono::prelude::*;
+use libp2p::bandwidth::BandwidthLogging;
 use libp2p::core::upgrade;
 use libp2p::futures::StreamExt;
+use libp2p::multiaddr::Protocol;
 use libp2p::noise::{Keypair, NoiseConfig, X25519Spec};
-use libp2p::swarm::SwarmBuilder;
+use libp2p::swarm::{AddressScore, ConnectionLimits, SwarmBuilder, SwarmEvent};
 use libp2p::tcp::TokioTcpConfig;
-use libp2p::{mplex, Swarm, Transport};
+use libp2p::{mplex, Multiaddr, PeerId, Swarm, Transport};
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -22,9 +29,17 @@ use tokio::sync::mpsc;
 use tokio::time::sleep;
 
 const DIFF_PREFIX: &str = "00";
+// caps how many connections the swarm will juggle at once, so a misbehaving
+// or buggy peer dialing us repeatedly can't exhaust our file descriptors.
+const MAX_ESTABLISHED_CONNECTIONS: u32 = 128;
 
 pub struct App {
     pub blocks: Vec<Block>,
+    pub mempool: tx::Mempool,
+    // this node's own nonce for transactions it originates.
+    pub next_nonce: u64,
+    // account balances, derived by replaying every accepted block's transactions.
+    pub ledger: ledger::Ledger,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -33,11 +48,20 @@ pub struct Block {
     pub hash: String,
     pub prev_hash: String,
     pub timestamp: i64,
-    pub data: String,
+    pub transactions: Vec<tx::Transaction>,
     pub nonce: u64,
 }
 
 // _____________UTILITIES_______________________________________________________
+// peer_id_from_multiaddr - pulls the trailing /p2p/<peer id> component off a
+// rendezvous server address, if present.
+fn peer_id_from_multiaddr(addr: &Multiaddr) -> Option<PeerId> {
+    addr.iter().find_map(|p| match p {
+        Protocol::P2p(hash) => PeerId::from_multihash(hash).ok(),
+        _ => None,
+    })
+}
+
 fn hash_to_bin(hash: &[u8]) -> String {
     let mut res: String = String::default();
     for c in hash {
@@ -46,11 +70,17 @@ fn hash_to_bin(hash: &[u8]) -> String {
     res
 }
 // calc_hash - calculates the next hash in the lineup
-fn calc_hash(id: u64, timestamp: i64, prev_hash: &str, data: &str, nonce: u64) -> Vec<u8> {
+fn calc_hash(
+    id: u64,
+    timestamp: i64,
+    prev_hash: &str,
+    transactions: &[tx::Transaction],
+    nonce: u64,
+) -> Vec<u8> {
     let data = serde_json::json!({
         "id": id,
         "previous_hash": prev_hash,
-        "data": data,
+        "transactions": transactions,
         "timestamp": timestamp,
         "nonce": nonce,
     });
@@ -58,7 +88,12 @@ fn calc_hash(id: u64, timestamp: i64, prev_hash: &str, data: &str, nonce: u64) -
     hasher.update(data.to_string().as_bytes());
     return hasher.finalize().as_slice().to_owned();
 }
-fn mine_block(id: u64, timestamp: i64, prev_hash: &str, data: &str) -> (u64, String) {
+fn mine_block(
+    id: u64,
+    timestamp: i64,
+    prev_hash: &str,
+    transactions: &[tx::Transaction],
+) -> (u64, String) {
     info!("mining block...");
     let mut nonce = 0;
 
@@ -68,7 +103,7 @@ fn mine_block(id: u64, timestamp: i64, prev_hash: &str, data: &str) -> (u64, Str
             info!("nonce: {}", nonce);
         }
 
-        let hash = calc_hash(id, timestamp, prev_hash, data, nonce);
+        let hash = calc_hash(id, timestamp, prev_hash, transactions, nonce);
         let bin_hash = hash_to_bin(&hash);
         if bin_hash.starts_with(DIFF_PREFIX) {
             info!(
@@ -88,7 +123,12 @@ fn mine_block(id: u64, timestamp: i64, prev_hash: &str, data: &str) -> (u64, Str
 // validation
 impl App {
     fn new() -> Self {
-        Self { blocks: vec![] }
+        Self {
+            blocks: vec![],
+            mempool: tx::Mempool::new(),
+            next_nonce: 0,
+            ledger: ledger::Ledger::new(),
+        }
     }
 
     // set_genesis - inits the genesis block.
@@ -97,10 +137,11 @@ impl App {
             id: 0,
             timestamp: Utc::now().timestamp(),
             prev_hash: String::from("genesis"),
-            data: String::from("genesis!"),
+            transactions: vec![],
             nonce: 2836,
             hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
         };
+        self.ledger.enact(&genensis_block);
         self.blocks.push(genensis_block);
     }
 
@@ -120,27 +161,55 @@ impl App {
                 block.id, prev.id
             );
             return false;
+        } else if let Some(bad_tx) = block
+            .transactions
+            .iter()
+            .find(|t| !t.is_signature_valid())
+        {
+            warn!(
+                "block with id {} has transaction {} with an invalid signature",
+                block.id, bad_tx.id
+            );
+            return false;
         } else if hex::encode(calc_hash(
             block.id,
             block.timestamp,
             &block.prev_hash,
-            &block.data,
+            &block.transactions,
             block.nonce,
         )) != block.hash
         {
             warn!("block with the id {} has invalid hash", block.id);
+            return false;
         }
         return true;
     }
-    // try_add_block - tries to add the block to the blockchain.
-    fn try_add_block(&mut self, block: Block) {
+    // try_add_block - tries to add the block to the blockchain. returns
+    // whether it was actually added, so callers can tell a rejected block
+    // (e.g. one they just mined themselves) from a successful one.
+    fn try_add_block(&mut self, block: Block) -> bool {
         let latest_block = self.blocks.last().expect("there is at least one block.");
-        // if the latest block is good to go, push to the block.
-        if self.is_block_valid(&block, latest_block) {
-            self.blocks.push(block);
-        } else {
+        // a well-formed header isn't enough - the transfers it describes have
+        // to be possible too.
+        if !self.is_block_valid(&block, latest_block) {
             eprintln!("could not add block - invalid op.");
+            return false;
+        }
+        if !self.ledger.enact(&block) {
+            warn!("block with id {} has an invalid state transition", block.id);
+            return false;
         }
+        self.mempool.remove_confirmed(&block.transactions);
+        self.blocks.push(block);
+        self.refresh_next_nonce();
+        true
+    }
+
+    // keeps `next_nonce` in step with the ledger's view of this node's own
+    // account, so a node that already had confirmed transactions before a
+    // restart/resync doesn't build its next `tx` against a stale nonce of 0.
+    fn refresh_next_nonce(&mut self) {
+        self.next_nonce = self.ledger.next_nonce(&p2p::peer_id().to_string());
     }
 
     // is_chain_valid - checks if our chain is valid. if not, fail the whole thing.
@@ -158,39 +227,46 @@ impl App {
         true
     }
     // choose_chain - chooses the longest chain when there is a mining conflict.
+    // a chain only counts as valid if it also replays cleanly through a fresh
+    // ledger from genesis - a longer chain of impossible transfers loses to a
+    // shorter chain that actually balances.
     fn choose_chain(&mut self, local: Vec<Block>, remote: Vec<Block>) -> Vec<Block> {
-        // check both the remote and local chains to see whats good.
-        let is_local_valid = self.is_chain_valid(&local);
-        let is_remote_valid = self.is_chain_valid(&remote);
+        let local_ledger = ledger::Ledger::enact_chain(&local);
+        let remote_ledger = ledger::Ledger::enact_chain(&remote);
+        let is_local_valid = self.is_chain_valid(&local) && local_ledger.is_some();
+        let is_remote_valid = self.is_chain_valid(&remote) && remote_ledger.is_some();
 
-        // check the validity against each chain.
-        if is_local_valid && is_remote_valid {
+        let (chosen, ledger) = if is_local_valid && is_remote_valid {
             if local.len() >= remote.len() {
-                local
+                (local, local_ledger.expect("checked valid above"))
             } else {
-                remote
+                (remote, remote_ledger.expect("checked valid above"))
             }
         } else if is_remote_valid && !is_local_valid {
-            remote
+            (remote, remote_ledger.expect("checked valid above"))
         } else if !is_remote_valid && is_local_valid {
-            local
+            (local, local_ledger.expect("checked valid above"))
         } else {
             panic!("local and remote chains are both invalid.");
-        }
+        };
+
+        self.ledger = ledger;
+        self.refresh_next_nonce();
+        chosen
     }
 }
 
 // ___________________________________BLOCK______________________________________________
 impl Block {
-    pub fn new(id: u64, prev_hash: String, data: String) -> Self {
+    pub fn new(id: u64, prev_hash: String, transactions: Vec<tx::Transaction>) -> Self {
         let now = Utc::now();
-        let (nonce, hash) = mine_block(id, now.timestamp(), &prev_hash, &data);
+        let (nonce, hash) = mine_block(id, now.timestamp(), &prev_hash, &transactions);
         Self {
             id,
             hash,
             timestamp: now.timestamp(),
             prev_hash,
-            data,
+            transactions,
             nonce,
         }
     }
@@ -200,13 +276,24 @@ async fn main() {
     // logs cool stuff
     pretty_env_logger::init();
 
-    info!("Peer Id: {}", p2p::PEER_ID.clone());
+    // load this node's persistent identity so its PeerId survives restarts.
+    let key_path = keys::resolve_key_path();
+    let node_keypair = keys::load_or_generate(&key_path);
+    p2p::init_peer_id(PeerId::from(node_keypair.public()));
+
+    info!("Peer Id: {}", p2p::peer_id());
     // no clue what this is, boutta figure it out.
-    let (response_sender, mut response_receiver) = mpsc::unbounded_channel();
     let (init_sender, mut init_receiver) = mpsc::unbounded_channel();
+    let (discover_sender, mut discover_receiver) = mpsc::unbounded_channel();
+
+    let rendezvous_addrs = cli::rendezvous_servers();
+    let rendezvous_server_ids: Vec<PeerId> = rendezvous_addrs
+        .iter()
+        .filter_map(peer_id_from_multiaddr)
+        .collect();
 
     let auth_keys = Keypair::<X25519Spec>::new()
-        .into_authentic(&p2p::KEYS)
+        .into_authentic(&node_keypair)
         .expect("can create auth keys");
 
     let transport = TokioTcpConfig::new()
@@ -215,12 +302,28 @@ async fn main() {
         .multiplex(mplex::MplexConfig::new())
         .boxed();
 
-    let behaviour = p2p::AppBehaviour::new(App::new(), response_sender, init_sender.clone()).await;
+    // logs bytes in/out so "ls net" has something to report.
+    let (transport, bandwidth) = BandwidthLogging::new(transport);
+    let transport = transport.boxed();
+
+    let behaviour = p2p::AppBehaviour::new(
+        App::new(),
+        node_keypair,
+        rendezvous_server_ids,
+        init_sender.clone(),
+        bandwidth,
+    )
+    .await;
 
-    let mut swarm = SwarmBuilder::new(transport, behaviour, *p2p::PEER_ID)
+    let connection_limits = ConnectionLimits::default()
+        .with_max_established_per_peer(Some(1))
+        .with_max_established(Some(MAX_ESTABLISHED_CONNECTIONS));
+
+    let mut swarm = SwarmBuilder::new(transport, behaviour, p2p::peer_id())
         .executor(Box::new(|f| {
             spawn(f);
         }))
+        .connection_limits(connection_limits)
         .build();
 
     let stdin = io::stdin();
@@ -233,64 +336,98 @@ async fn main() {
     )
     .expect("swarm can be started");
 
+    // rendezvous only advertises addresses the swarm has confirmed as
+    // external, and nothing here does NAT traversal to learn one on its own.
+    if let Some(addr) = cli::external_address() {
+        info!("declaring external address: {}", addr);
+        swarm.add_external_address(addr, AddressScore::Infinite);
+    }
+
+    for addr in rendezvous_addrs {
+        if let Err(e) = Swarm::dial(&mut swarm, addr.clone()) {
+            error!("error dialing rendezvous server {}: {:?}", addr, e);
+        }
+    }
+
     spawn(async move {
         sleep(Duration::from_secs(1)).await;
         info!("sending init event");
         init_sender.send(true).expect("can send init event");
     });
 
+    spawn(async move {
+        loop {
+            sleep(Duration::from_secs(30)).await;
+            if discover_sender.send(true).is_err() {
+                break;
+            }
+        }
+    });
+
     loop {
         let evt = {
             select! {
                 line = lines.next_line() => Some(p2p::EventType::Input(line.expect("can get line").expect("can read line from stdin"))),
-                response = response_receiver.recv() =>{
-                    Some(p2p::EventType::LocalChainResponse(response.expect("response exists")))
-                },
                    _init = init_receiver.recv() => {
                        Some(p2p::EventType::Init)
                 }
+                _tick = discover_receiver.recv() => {
+                    Some(p2p::EventType::DiscoverTick)
+                }
                 event = swarm.select_next_some() => {
+                    if let SwarmEvent::ConnectionEstablished { peer_id, .. } = &event {
+                        if swarm.behaviour().rendezvous_servers.contains_key(peer_id) {
+                            let server = *peer_id;
+                            swarm.behaviour_mut().register_with_rendezvous(server);
+                            swarm.behaviour_mut().discover_via_rendezvous(server);
+                        }
+                    }
                     info!("unhandled swarm event: {:?}", event);
                     None
                 },
             }
         };
+        // dial anything a rendezvous Discovered queued up while polling the
+        // swarm above - inject_event has no way to reach the Swarm itself.
+        p2p::drain_pending_dials(&mut swarm);
         if let Some(event) = evt {
             match event {
                 p2p::EventType::Init => {
-                    let peers = p2p::get_list_peers(&swarm);
+                    // WAN peers found purely via rendezvous have no mdns
+                    // entry, so they'd otherwise never be offered as a sync
+                    // source even after they're connected.
+                    let mut peers = p2p::get_list_peers(&swarm);
+                    peers.extend(p2p::get_rendezvous_peers(&swarm));
                     swarm.behaviour_mut().app.set_genesis();
 
                     info!("connected nodes: {}", peers.len());
-                    if !peers.is_empty() {
-                        let req = p2p::LocalChainRequest {
-                            from_peer_id: peers
-                                .iter()
-                                .last()
-                                .expect("at least one peer")
-                                .to_string(),
-                        };
-
-                        let json_data =
-                            serde_json::to_string(&req).expect("can parse request to json");
-                        swarm
-                            .behaviour_mut()
-                            .floodsub
-                            .publish(p2p::CHAIN_TOPIC.clone(), json_data.as_bytes());
+                    if let Some(sync_peer) = peers.into_iter().last() {
+                        // sync directly from one peer instead of broadcasting the request.
+                        swarm.behaviour_mut().chain_sync.send_request(
+                            &sync_peer,
+                            chain_sync::ChainRequest {
+                                from: p2p::peer_id().to_string(),
+                            },
+                        );
                     }
                 }
 
-                p2p::EventType::LocalChainResponse(res) => {
-                    let json_data = serde_json::to_string(&res).expect("can parse res to json");
-                    swarm
-                        .behaviour_mut()
-                        .floodsub
-                        .publish(p2p::CHAIN_TOPIC.clone(), json_data.as_bytes());
+                p2p::EventType::DiscoverTick => {
+                    let servers: Vec<PeerId> =
+                        swarm.behaviour().rendezvous_servers.keys().copied().collect();
+                    for server in servers {
+                        swarm.behaviour_mut().discover_via_rendezvous(server);
+                    }
                 }
+
                 p2p::EventType::Input(line) => match line.as_str() {
                     "ls p" => p2p::print_peers(&swarm),
+                    "ls s" => p2p::print_rendezvous_peers(&swarm),
                     cmd if cmd.starts_with("ls c") => p2p::print_chain(&swarm),
-                    cmd if cmd.starts_with("create b") => p2p::create_block(cmd, &mut swarm),
+                    cmd if cmd.starts_with("ls bal") => p2p::print_balances(&swarm),
+                    cmd if cmd.starts_with("ls net") => p2p::print_net(&swarm),
+                    cmd if cmd.starts_with("create b") => p2p::create_block(&mut swarm),
+                    cmd if cmd.starts_with("tx ") => p2p::create_transaction(cmd, &mut swarm),
                     _ => error!("unknown command"),
                 },
             }
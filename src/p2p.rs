@@ -1,47 +1,89 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::sync::Arc;
 
 use super::{App, Block};
+use crate::chain_sync::{ChainRequest, ChainResponse, ChainSyncCodec, ChainSyncProtocol};
+use crate::tx::Transaction;
 use libp2p::{
-    floodsub::{Floodsub, FloodsubEvent, Topic},
+    bandwidth::BandwidthSinks,
+    gossipsub::{
+        Gossipsub, GossipsubConfigBuilder, GossipsubEvent, GossipsubMessage, IdentTopic,
+        MessageAcceptance, MessageAuthenticity, PeerScoreParams, PeerScoreThresholds,
+        TopicScoreParams, ValidationMode,
+    },
     identity,
     mdns::{Mdns, MdnsEvent},
+    multiaddr::Protocol,
+    rendezvous::{
+        client::{Behaviour as Rendezvous, Event as RendezvousEvent},
+        Cookie, Namespace,
+    },
+    request_response::{
+        ProtocolSupport, RequestResponse, RequestResponseConfig, RequestResponseEvent,
+        RequestResponseMessage,
+    },
     swarm::{NetworkBehaviourEventProcess, Swarm},
-    NetworkBehaviour, PeerId,
+    Multiaddr, NetworkBehaviour, PeerId,
 };
-use log::{error, info};
-use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
+use log::{error, info, warn};
+use once_cell::sync::{Lazy, OnceCell};
 use tokio::sync::mpsc;
 
-// keypair and peer ID.
-// helps identy a client on the p2p network.
-pub static KEYS: Lazy<identity::Keypair> = Lazy::new(identity::Keypair::generate_ed25519);
-pub static PEER_ID: Lazy<PeerId> = Lazy::new(|| PeerId::from(KEYS.public()));
-pub static CHAIN_TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new("chains"));
-pub static BLOCK_TOPIC: Lazy<Topic> = Lazy::new(|| Topic::new("blocks"));
-
-#[derive(Debug, Deserialize, Serialize)]
-// holds llist of blocks ,and a receiver.
-// this is the struct we expect when someone sends ups their local chain and use to send out our chain.
-pub struct ChainResponse {
-    pub blocks: Vec<Block>,
-    pub receiver: String,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-/*
-triggers the interaction between nodes sharing info.
-if we send localchainrequest with a peer_id of another node in the system
-it will trigger that they send us  their chain back.
-*/
-pub struct LocalChainRequest {
-    pub from_peer_id: String,
+// the namespace our nodes register themselves under on a rendezvous server.
+const RENDEZVOUS_NAMESPACE: &str = "blockchain";
+
+// the node's peer ID, derived from the keypair loaded (or generated) in main.
+// set once via init_peer_id before the swarm is built, so it's stable across restarts.
+pub static PEER_ID: OnceCell<PeerId> = OnceCell::new();
+// `blocks` carries new-block gossip only; chain sync now goes over a
+// dedicated request/response protocol instead of a broadcast topic.
+pub static BLOCK_TOPIC: Lazy<IdentTopic> = Lazy::new(|| IdentTopic::new("blocks"));
+pub static TX_TOPIC: Lazy<IdentTopic> = Lazy::new(|| IdentTopic::new("tx"));
+
+// peer score thresholds below which gossipsub will graylist/disconnect a peer.
+// a node that keeps gossiping invalid blocks walks its score down through these
+// until it's pruned from the mesh.
+fn peer_score_params() -> PeerScoreParams {
+    let topic_params = TopicScoreParams {
+        topic_weight: 1.0,
+        invalid_message_deliveries_weight: -50.0,
+        invalid_message_deliveries_decay: 0.5,
+        ..Default::default()
+    };
+    let mut params = PeerScoreParams {
+        behaviour_penalty_weight: -10.0,
+        ..Default::default()
+    };
+    params.topics.insert(BLOCK_TOPIC.hash(), topic_params.clone());
+    params.topics.insert(TX_TOPIC.hash(), topic_params);
+    params
+}
+
+fn peer_score_thresholds() -> PeerScoreThresholds {
+    PeerScoreThresholds {
+        gossip_threshold: -10.0,
+        publish_threshold: -20.0,
+        graylist_threshold: -80.0,
+        ..Default::default()
+    }
+}
+
+// peer_id - this node's stable PeerId. panics if called before init_peer_id.
+pub fn peer_id() -> PeerId {
+    *PEER_ID.get().expect("peer id initialized in main before use")
+}
+
+// init_peer_id - called once from main, right after the node's keypair is loaded.
+pub fn init_peer_id(id: PeerId) {
+    PEER_ID.set(id).expect("peer id initialized only once");
 }
+
 // handles incoming messages, input, and initialization.
 pub enum EventType {
-    LocalChainResponse(ChainResponse),
     Input(String),
     Init,
+    DiscoverTick,
 }
 
 // Network Behavior:
@@ -50,39 +92,121 @@ pub enum EventType {
 #[derive(NetworkBehaviour)]
 //
 pub struct AppBehaviour {
-    pub floodsub: Floodsub,
+    pub gossipsub: Gossipsub,
     pub mdns: Mdns,
-    // macros not working?
-    #[behaviour(ignore)]
-    pub response_sender: mpsc::UnboundedSender<ChainResponse>,
+    pub chain_sync: RequestResponse<ChainSyncCodec>,
+    pub rendezvous: Rendezvous,
     #[behaviour(ignore)]
     pub init_sender: mpsc::UnboundedSender<bool>,
     #[behaviour(ignore)]
     pub app: App,
+    // this node's identity keypair; create_transaction signs with it.
+    #[behaviour(ignore)]
+    pub keypair: identity::Keypair,
+    // rendezvous servers we've registered with, and the cookie from their last
+    // Discovered response (lets the next discover() request be incremental).
+    #[behaviour(ignore)]
+    pub rendezvous_servers: HashMap<PeerId, Option<Cookie>>,
+    // peers learned via rendezvous discovery, kept separate from mdns's view so
+    // `ls s` can report WAN peers distinctly from LAN ones.
+    #[behaviour(ignore)]
+    pub rendezvous_peers: HashSet<PeerId>,
+    // total bytes moved by the transport, for `ls net`.
+    #[behaviour(ignore)]
+    pub bandwidth: Arc<BandwidthSinks>,
+    // running counts for `ls net`, independent of mempool/chain churn.
+    #[behaviour(ignore)]
+    pub blocks_seen: u64,
+    #[behaviour(ignore)]
+    pub transactions_seen: u64,
+    // addresses queued up to dial from the main loop - inject_event only has
+    // &mut self, not &mut Swarm, so a rendezvous Discovered can't dial directly.
+    #[behaviour(ignore)]
+    pub pending_dials: Vec<Multiaddr>,
 }
 // App Behavior handler and function.
 impl AppBehaviour {
     pub async fn new(
         app: App,
-        response_sender: mpsc::UnboundedSender<ChainResponse>,
+        keypair: identity::Keypair,
+        rendezvous_servers: Vec<PeerId>,
         init_sender: mpsc::UnboundedSender<bool>,
+        bandwidth: Arc<BandwidthSinks>,
     ) -> Self {
+        // validate_messages() defers acceptance to us, via report_message_validation_result,
+        // instead of gossipsub auto-accepting everything that deserializes.
+        let gossipsub_config = GossipsubConfigBuilder::default()
+            .validation_mode(ValidationMode::Strict)
+            .validate_messages()
+            .build()
+            .expect("valid gossipsub config");
+
+        let mut gossipsub = Gossipsub::new(
+            MessageAuthenticity::Signed(keypair.clone()),
+            gossipsub_config,
+        )
+        .expect("valid gossipsub behaviour config");
+        gossipsub
+            .with_peer_score(peer_score_params(), peer_score_thresholds())
+            .expect("can set gossipsub peer scoring");
+
+        let chain_sync = RequestResponse::new(
+            ChainSyncCodec::default(),
+            iter::once((ChainSyncProtocol, ProtocolSupport::Full)),
+            RequestResponseConfig::default(),
+        );
+
+        let rendezvous = Rendezvous::new(keypair.clone());
+
         let mut behaviour = Self {
             app,
-            floodsub: Floodsub::new(*PEER_ID),
+            gossipsub,
+            chain_sync,
+            rendezvous,
             mdns: Mdns::new(Default::default())
                 .await
                 .expect("can create mdns conn."),
-            response_sender,
             init_sender,
+            keypair,
+            rendezvous_servers: rendezvous_servers.into_iter().map(|id| (id, None)).collect(),
+            rendezvous_peers: HashSet::new(),
+            bandwidth,
+            blocks_seen: 0,
+            transactions_seen: 0,
+            pending_dials: Vec::new(),
         };
-        // what does clone do?
-        behaviour.floodsub.subscribe(CHAIN_TOPIC.clone());
         // clone returns a copy of value.
-        behaviour.floodsub.subscribe(BLOCK_TOPIC.clone());
+        behaviour
+            .gossipsub
+            .subscribe(&BLOCK_TOPIC)
+            .expect("can subscribe to blocks topic");
+        behaviour
+            .gossipsub
+            .subscribe(&TX_TOPIC)
+            .expect("can subscribe to tx topic");
 
         return behaviour;
     }
+
+    // register_with_rendezvous - announces our external address under the
+    // "blockchain" namespace once we've connected to a known rendezvous server.
+    pub fn register_with_rendezvous(&mut self, server: PeerId) {
+        let namespace = Namespace::new(RENDEZVOUS_NAMESPACE.to_string())
+            .expect("namespace is a valid rendezvous namespace");
+        if let Err(e) = self.rendezvous.register(namespace, server, None) {
+            error!("error registering with rendezvous server {}: {:?}", server, e);
+        }
+    }
+
+    // discover_via_rendezvous - asks a known rendezvous server for peers in our
+    // namespace, picking up from wherever its last Discovered cookie left off.
+    pub fn discover_via_rendezvous(&mut self, server: PeerId) {
+        let cookie = self.rendezvous_servers.get(&server).cloned().flatten();
+        let namespace = Namespace::new(RENDEZVOUS_NAMESPACE.to_string())
+            .expect("namespace is a valid rendezvous namespace");
+        self.rendezvous
+            .discover(Some(namespace), cookie, None, server);
+    }
 }
 
 impl NetworkBehaviourEventProcess<MdnsEvent> for AppBehaviour {
@@ -92,61 +216,224 @@ impl NetworkBehaviourEventProcess<MdnsEvent> for AppBehaviour {
         match event {
             MdnsEvent::Discovered(discovered_list) => {
                 for (peer, _addr) in discovered_list {
-                    self.floodsub.add_node_to_partial_view(peer);
+                    self.gossipsub.add_explicit_peer(&peer);
                 }
             }
             MdnsEvent::Expired(expired_list) => {
                 for (peer, _addr) in expired_list {
                     // if has_node is false
                     if !self.mdns.has_node(&peer) {
-                        self.floodsub.remove_node_from_partial_view(&peer);
+                        self.gossipsub.remove_explicit_peer(&peer);
                     }
                 }
             }
         }
     }
 }
-// Handle network messages from other nodes.
-impl NetworkBehaviourEventProcess<FloodsubEvent> for AppBehaviour {
-    fn inject_event(&mut self, event: FloodsubEvent) {
-        //
-        // pass down the floodsub msg we work with.
-        if let FloodsubEvent::Message(msg) = event {
-            //
-            // Ok (result ) type pulled from Chain Response serde_json.
-            if let Ok(response) = serde_json::from_slice::<ChainResponse>(&msg.data) {
-                //
-                if response.receiver == PEER_ID.to_string() {
-                    info!("response from {}: ", msg.source);
-                    response.blocks.iter().for_each(|i| info!("{:?}", i));
 
+// validate_message - decides whether a gossiped message should be Accept-ed,
+// Reject-ed (bad peer, score goes down), or Ignore-d (not for us, score unaffected).
+fn validate_message(app: &App, message: &GossipsubMessage) -> MessageAcceptance {
+    if message.topic == BLOCK_TOPIC.hash() {
+        return match serde_json::from_slice::<Block>(&message.data) {
+            Ok(block) => match app.blocks.last() {
+                Some(tip) if app.is_block_valid(&block, tip) => {
+                    // a well-formed header isn't enough - replay it against a
+                    // throwaway copy of the ledger before letting it amplify
+                    // out to the rest of the mesh.
+                    let mut ledger = app.ledger.clone();
+                    if ledger.enact(&block) {
+                        MessageAcceptance::Accept
+                    } else {
+                        warn!(
+                            "rejecting gossiped block id {} with an invalid state transition",
+                            block.id
+                        );
+                        MessageAcceptance::Reject
+                    }
+                }
+                Some(_) => {
+                    warn!("rejecting invalid gossiped block id: {}", block.id);
+                    MessageAcceptance::Reject
+                }
+                None => MessageAcceptance::Ignore,
+            },
+            // unrecognized payload on the blocks topic - reject so the sender's score drops.
+            Err(_) => MessageAcceptance::Reject,
+        };
+    }
+    if message.topic == TX_TOPIC.hash() {
+        return match serde_json::from_slice::<Transaction>(&message.data) {
+            Ok(transaction) if transaction.is_signature_valid() => MessageAcceptance::Accept,
+            Ok(transaction) => {
+                warn!(
+                    "rejecting transaction {} with invalid signature",
+                    transaction.id
+                );
+                MessageAcceptance::Reject
+            }
+            Err(_) => MessageAcceptance::Reject,
+        };
+    }
+    MessageAcceptance::Ignore
+}
+
+// Handle block and transaction gossip from other nodes.
+impl NetworkBehaviourEventProcess<GossipsubEvent> for AppBehaviour {
+    fn inject_event(&mut self, event: GossipsubEvent) {
+        if let GossipsubEvent::Message {
+            propagation_source,
+            message_id,
+            message,
+        } = event
+        {
+            let acceptance = validate_message(&self.app, &message);
+            if let Err(e) =
+                self.gossipsub
+                    .report_message_validation_result(&message_id, &propagation_source, acceptance)
+            {
+                error!("error reporting message validation result, {}", e);
+            }
+            if acceptance != MessageAcceptance::Accept {
+                return;
+            }
+
+            if message.topic == BLOCK_TOPIC.hash() {
+                if let Ok(block) = serde_json::from_slice::<Block>(&message.data) {
+                    info!("received new block from {}", propagation_source);
+                    self.app.try_add_block(block);
+                    self.blocks_seen += 1;
+                }
+            } else if message.topic == TX_TOPIC.hash() {
+                if let Ok(transaction) = serde_json::from_slice::<Transaction>(&message.data) {
+                    info!(
+                        "received transaction {} from {}",
+                        transaction.id, propagation_source
+                    );
+                    self.app.mempool.insert(transaction);
+                    self.transactions_seen += 1;
+                }
+            }
+        }
+    }
+}
+
+// Handle directed chain-sync requests and responses.
+impl NetworkBehaviourEventProcess<RequestResponseEvent<ChainRequest, ChainResponse>>
+    for AppBehaviour
+{
+    fn inject_event(&mut self, event: RequestResponseEvent<ChainRequest, ChainResponse>) {
+        match event {
+            RequestResponseEvent::Message { peer, message } => match message {
+                RequestResponseMessage::Request {
+                    request, channel, ..
+                } => {
+                    info!("sending local chain to {} ({})", peer, request.from);
+                    if let Err(e) = self.chain_sync.send_response(
+                        channel,
+                        ChainResponse {
+                            blocks: self.app.blocks.clone(),
+                        },
+                    ) {
+                        error!("error sending chain sync response, {:?}", e);
+                    }
+                }
+                RequestResponseMessage::Response { response, .. } => {
+                    info!("chain from {}: ", peer);
+                    response.blocks.iter().for_each(|b| info!("{:?}", b));
                     self.app.blocks = self
                         .app
-                        .choose_chain(self.app.blocks.clone(), response.blocks)
+                        .choose_chain(self.app.blocks.clone(), response.blocks);
                 }
-                // if result Ok from LocalChainRequest type
-            } else if let Ok(response) = serde_json::from_slice::<LocalChainRequest>(&msg.data) {
-                // we add types to the serde function to enable typing down the line.
-                info!("Sending local chain to id: {}", msg.source.to_string());
-                let peer_id = response.from_peer_id;
-                if PEER_ID.to_string() == peer_id {
-                    if let Err(e) = self.response_sender.send(ChainResponse {
-                        blocks: self.app.blocks.clone(),
-                        receiver: msg.source.to_string(),
-                    }) {
-                        error!("error sending request vial channel, {}", e);
+            },
+            RequestResponseEvent::OutboundFailure { peer, error, .. } => {
+                error!("chain sync request to {} failed: {:?}", peer, error);
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                error!("chain sync response to {} failed: {:?}", peer, error);
+            }
+            RequestResponseEvent::ResponseSent { .. } => {}
+        }
+    }
+}
+
+// Handle rendezvous registration/discovery results.
+impl NetworkBehaviourEventProcess<RendezvousEvent> for AppBehaviour {
+    fn inject_event(&mut self, event: RendezvousEvent) {
+        match event {
+            RendezvousEvent::Registered {
+                rendezvous_node,
+                ttl,
+                namespace,
+            } => {
+                info!(
+                    "registered with rendezvous server {} under '{}' for {}s",
+                    rendezvous_node, namespace, ttl
+                );
+            }
+            RendezvousEvent::RegisterFailed(error) => {
+                error!("rendezvous registration failed: {:?}", error);
+            }
+            RendezvousEvent::Discovered {
+                rendezvous_node,
+                registrations,
+                cookie,
+            } => {
+                self.rendezvous_servers
+                    .insert(rendezvous_node, Some(cookie));
+                for registration in registrations {
+                    let peer = registration.record.peer_id();
+                    if peer == peer_id() {
+                        continue;
+                    }
+                    info!("discovered peer {} via rendezvous", peer);
+                    self.rendezvous_peers.insert(peer);
+                    self.gossipsub.add_explicit_peer(&peer);
+                    // a discovered record is just a PeerId + addresses - nothing
+                    // dials it on our behalf, so without this a WAN peer found
+                    // via rendezvous is never actually reachable.
+                    for address in registration.record.addresses() {
+                        let dial_addr = if address.iter().any(|p| matches!(p, Protocol::P2p(_))) {
+                            address.clone()
+                        } else {
+                            address.clone().with(Protocol::P2p(peer.as_ref().clone()))
+                        };
+                        self.pending_dials.push(dial_addr);
                     }
                 }
-            } else if let Ok(block) = serde_json::from_slice::<Block>(&msg.data) {
-                info!("received new block from {}", msg.source.to_string());
-                self.app.try_add_block(block);
             }
+            RendezvousEvent::DiscoverFailed {
+                rendezvous_node,
+                error,
+                ..
+            } => {
+                error!(
+                    "rendezvous discovery via {} failed: {:?}",
+                    rendezvous_node, error
+                );
+            }
+            RendezvousEvent::Expired { peer } => {
+                self.rendezvous_peers.remove(&peer);
+                self.gossipsub.remove_explicit_peer(&peer);
+            }
+        }
+    }
+}
+
+// drain_pending_dials - dials every address a rendezvous Discovered queued up
+// since the last call. has to live outside inject_event, which only gets
+// &mut self and has no way to reach the Swarm to dial anything.
+pub fn drain_pending_dials(swarm: &mut Swarm<AppBehaviour>) {
+    let addrs = std::mem::take(&mut swarm.behaviour_mut().pending_dials);
+    for addr in addrs {
+        if let Err(e) = Swarm::dial(swarm, addr.clone()) {
+            error!("error dialing rendezvous-discovered peer {}: {:?}", addr, e);
         }
     }
 }
 
 // get_list_peers - gets the list of all discovered peers in the network.
-pub fn get_list_peers(swarm: &Swarm<AppBehaviour>) -> Vec<String> {
+pub fn get_list_peers(swarm: &Swarm<AppBehaviour>) -> Vec<PeerId> {
     info!("Discovered network peers: ");
 
     //
@@ -158,8 +445,7 @@ pub fn get_list_peers(swarm: &Swarm<AppBehaviour>) -> Vec<String> {
     for &peer in nodes {
         unique_peers.insert(peer);
     }
-    // creates a closure and modifies the data in place.
-    unique_peers.iter().map(|p| p.to_string()).collect()
+    unique_peers.into_iter().collect()
 }
 
 pub fn print_peers(swarm: &Swarm<AppBehaviour>) {
@@ -168,6 +454,18 @@ pub fn print_peers(swarm: &Swarm<AppBehaviour>) {
     peers.iter().for_each(|p| info!("{}", p))
 }
 
+// get_rendezvous_peers - peers learned via rendezvous discovery, kept apart
+// from mdns's view (get_list_peers) so WAN peers can be told apart from LAN.
+pub fn get_rendezvous_peers(swarm: &Swarm<AppBehaviour>) -> Vec<PeerId> {
+    swarm.behaviour().rendezvous_peers.iter().copied().collect()
+}
+
+// print_rendezvous_peers - "ls s": peers learned via rendezvous, shown separately from mdns.
+pub fn print_rendezvous_peers(swarm: &Swarm<AppBehaviour>) {
+    info!("Peers learned via rendezvous: ");
+    get_rendezvous_peers(swarm).iter().for_each(|p| info!("{}", p));
+}
+
 pub fn print_chain(swarm: &Swarm<AppBehaviour>) {
     info!("Local Blockcahin:");
     let pretty_json = serde_json::to_string_pretty(&swarm.behaviour().app.blocks)
@@ -175,22 +473,96 @@ pub fn print_chain(swarm: &Swarm<AppBehaviour>) {
     info!("{}", pretty_json);
 }
 
-pub fn create_block(cmd: &str, swarm: &mut Swarm<AppBehaviour>) {
-    if let Some(data) = cmd.strip_prefix("create b") {
-        let behaviour = swarm.behaviour_mut();
-        let latest_block = behaviour.app.blocks.last().expect("at least one block");
-        // construct new block
-        let block = Block::new(
-            latest_block.id + 1,
-            latest_block.hash.clone(),
-            data.to_owned(),
-        );
-        let json_data = serde_json::to_string(&block).expect("can parse request to json");
-        behaviour.app.blocks.push(block);
-        info!("broadcasting new block to network");
-        // finally, publish the block and data to the network.
-        behaviour
-            .floodsub
-            .publish(BLOCK_TOPIC.clone(), json_data.as_bytes());
+// print_balances - "ls bal": account balances as of the locally accepted chain.
+pub fn print_balances(swarm: &Swarm<AppBehaviour>) {
+    info!("Account balances:");
+    swarm
+        .behaviour()
+        .app
+        .ledger
+        .balances()
+        .iter()
+        .for_each(|(account, balance)| info!("{}: {}", account, balance));
+}
+
+// print_net - "ls net": connection count, bandwidth, and chain activity since start.
+pub fn print_net(swarm: &Swarm<AppBehaviour>) {
+    let peer_count = swarm.connected_peers().count();
+    let behaviour = swarm.behaviour();
+    info!("connected peers: {}", peer_count);
+    info!(
+        "bandwidth - inbound: {} bytes, outbound: {} bytes",
+        behaviour.bandwidth.total_inbound(),
+        behaviour.bandwidth.total_outbound()
+    );
+    info!(
+        "seen since start - blocks: {}, transactions: {}",
+        behaviour.blocks_seen, behaviour.transactions_seen
+    );
+}
+
+pub fn create_block(swarm: &mut Swarm<AppBehaviour>) {
+    let behaviour = swarm.behaviour_mut();
+    let latest_block = behaviour.app.blocks.last().expect("at least one block");
+    let transactions = behaviour.app.mempool.drain_for_block();
+    let drained = transactions.clone();
+    // construct new block
+    let block = Block::new(latest_block.id + 1, latest_block.hash.clone(), transactions);
+
+    // route it through the same validity + state-transition check every other
+    // block goes through - a drain that pulled mutually conflicting txs (e.g.
+    // the same sender's nonce twice) would otherwise fork this node onto a
+    // chain the rest of the network rejects outright.
+    let json_data = serde_json::to_string(&block).expect("can parse request to json");
+    if !behaviour.app.try_add_block(block) {
+        error!("mined block failed its own validity check - returning its transactions to the mempool");
+        for transaction in drained {
+            behaviour.app.mempool.insert(transaction);
+        }
+        return;
+    }
+    behaviour.blocks_seen += 1;
+    info!("broadcasting new block to network");
+    // finally, publish the block and data to the network.
+    if let Err(e) = behaviour
+        .gossipsub
+        .publish(BLOCK_TOPIC.clone(), json_data.as_bytes())
+    {
+        error!("error broadcasting block, {}", e);
+    }
+}
+
+// create_transaction - "tx <to> <amount>": builds, signs, and gossips a transaction.
+pub fn create_transaction(cmd: &str, swarm: &mut Swarm<AppBehaviour>) {
+    let args = match cmd.strip_prefix("tx ") {
+        Some(args) => args,
+        None => {
+            error!("usage: tx <to> <amount>");
+            return;
+        }
+    };
+    let mut parts = args.split_whitespace();
+    let (to, amount) = match (parts.next(), parts.next().and_then(|a| a.parse::<u64>().ok())) {
+        (Some(to), Some(amount)) => (to.to_string(), amount),
+        _ => {
+            error!("usage: tx <to> <amount>");
+            return;
+        }
+    };
+
+    let behaviour = swarm.behaviour_mut();
+    let nonce = behaviour.app.next_nonce;
+    let transaction = Transaction::new(&behaviour.keypair, to, amount, nonce);
+    behaviour.app.next_nonce += 1;
+
+    let json_data = serde_json::to_string(&transaction).expect("can parse transaction to json");
+    behaviour.app.mempool.insert(transaction);
+    behaviour.transactions_seen += 1;
+    info!("broadcasting transaction to network");
+    if let Err(e) = behaviour
+        .gossipsub
+        .publish(TX_TOPIC.clone(), json_data.as_bytes())
+    {
+        error!("error broadcasting transaction, {}", e);
     }
 }
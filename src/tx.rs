@@ -0,0 +1,106 @@
+// Transactions and the pending-transaction pool.
+// Turns the chain from a log of free-form strings into something that can
+// actually move value between accounts.
+
+use libp2p::{identity, PeerId};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// a block only ever carries this many pending transactions at a time, so a
+// burst of gossiped transactions can't force an unbounded mining delay.
+pub const MAX_TX_PER_BLOCK: usize = 10;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Transaction {
+    pub id: String,
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+    pub nonce: u64,
+    pub signature: Vec<u8>,
+}
+
+impl Transaction {
+    // `from` comes straight out of the keypair, so a node can only ever spend
+    // its own funds - no way to forge someone else's sender field.
+    pub fn new(keypair: &identity::Keypair, to: String, amount: u64, nonce: u64) -> Self {
+        let from = PeerId::from(keypair.public()).to_string();
+        let id = Self::compute_id(&from, &to, amount, nonce);
+        let signature = keypair.sign(id.as_bytes()).expect("can sign transaction");
+        Self {
+            id,
+            from,
+            to,
+            amount,
+            nonce,
+            signature,
+        }
+    }
+
+    fn compute_id(from: &str, to: &str, amount: u64, nonce: u64) -> String {
+        let payload = serde_json::json!({
+            "from": from,
+            "to": to,
+            "amount": amount,
+            "nonce": nonce,
+        });
+        let mut hasher = Sha256::new();
+        hasher.update(payload.to_string().as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    // recompute the id from the fields and check the signature against
+    // whatever public key is embedded in `from`.
+    pub fn is_signature_valid(&self) -> bool {
+        if Self::compute_id(&self.from, &self.to, self.amount, self.nonce) != self.id {
+            return false;
+        }
+        let from_peer: PeerId = match self.from.parse() {
+            Ok(peer) => peer,
+            Err(_) => return false,
+        };
+        match public_key_of(&from_peer) {
+            Some(public_key) => public_key.verify(self.id.as_bytes(), &self.signature),
+            None => false,
+        }
+    }
+}
+
+// works because small keys (ed25519 included) get embedded directly in the
+// PeerId's identity multihash instead of being hashed away.
+fn public_key_of(peer: &PeerId) -> Option<identity::PublicKey> {
+    identity::PublicKey::from_protobuf_encoding(peer.as_ref().digest()).ok()
+}
+
+// transactions that have been gossiped/created but not yet mined into a block.
+#[derive(Debug, Default)]
+pub struct Mempool {
+    pending: Vec<Transaction>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // ignore a transaction that's already pending. one that has left the pool
+    // (drained or confirmed) is not remembered and goes back in.
+    pub fn insert(&mut self, transaction: Transaction) {
+        if self.pending.iter().any(|t| t.id == transaction.id) {
+            return;
+        }
+        self.pending.push(transaction);
+    }
+
+    // up to MAX_TX_PER_BLOCK pending transactions for the next block.
+    pub fn drain_for_block(&mut self) -> Vec<Transaction> {
+        let take = MAX_TX_PER_BLOCK.min(self.pending.len());
+        self.pending.drain(..take).collect()
+    }
+
+    // drop whatever just landed in a newly-accepted block.
+    pub fn remove_confirmed(&mut self, confirmed: &[Transaction]) {
+        self.pending
+            .retain(|t| !confirmed.iter().any(|c| c.id == t.id));
+    }
+}